@@ -7,11 +7,274 @@ This source file implements zk-proof batch verifier functionality.
 use rand_core::RngCore;
 use crate::index::{VerifierIndex as Index};
 use oracle::rndoracle::{ProofError};
-pub use super::prover::{ProverProof, RandomOracles};
+pub use super::prover::{ProverProof, RandomOracles, ProofEvaluations};
 use algebra::{Field, PrimeField, PairingEngine, ProjectiveCurve, VariableBaseMSM};
 use oracle::sponge::FqSponge;
 use crate::plonk_sponge::FrSponge;
-use ff_fft::Evaluations;
+
+// A challenge squeezed from the sponge as 128 bits rather than a full field element, then
+// expanded into one via the Halo endomorphism map (`to_field`, below). Only the expanded `Fr` is
+// ever used downstream in this file; none of `verify`'s multi-scalar-muls scale a commitment
+// through the curve endomorphism itself, so this buys transcript compactness (fewer bits
+// squeezed per challenge), not a faster scalar mul.
+#[derive(Clone, Copy)]
+pub struct ScalarChallenge(pub u128);
+
+impl ScalarChallenge
+{
+    // Expands this 128-bit challenge into a full scalar field element,
+    // using the cube-root-of-unity endomorphism coefficient `endo_coeff`.
+    pub fn to_field<F: PrimeField>(&self, endo_coeff: &F) -> F
+    {
+        let mut acc = (*endo_coeff + &F::one()).double();
+
+        for i in (0..64).rev()
+        {
+            let should_negate = (self.0 >> (2 * i + 1)) & 1 == 1;
+            let should_endo = (self.0 >> (2 * i)) & 1 == 1;
+
+            let mut q = if should_negate { -F::one() } else { F::one() };
+            if should_endo { q *= endo_coeff; }
+
+            acc = acc + &q + &acc;
+        }
+
+        acc
+    }
+}
+
+// A labeled transcript for non-interactive challenge derivation. Every absorb/squeeze is tagged
+// with an operation label, and each protocol phase opens with a domain-separation tag, so the
+// exact sequence can be audited independently of which concrete sponge backs it. `oracles` and
+// `verify` are generic over `T: Transcript<E>`, constructing their working transcript via `new`
+// rather than building a `SpongeTranscript` directly, so swapping in a Poseidon or
+// Blake2b/Keccak transcript for cross-ecosystem interoperability is a matter of a new impl of
+// this trait (including its own `new`) and a different turbofish at the call site -- no change
+// to `oracles`/`verify` themselves.
+pub trait Transcript<E: PairingEngine>
+{
+    fn new(index: &Index<E>) -> Self;
+    fn domain_separator(&mut self, label: &'static str);
+    fn absorb_commitment(&mut self, label: &'static str, commitment: &E::G1Affine);
+    fn absorb_scalar(&mut self, label: &'static str, scalar: &E::Fr);
+    fn squeeze_challenge(&mut self, label: &'static str) -> E::Fr;
+    fn squeeze_challenge_128(&mut self, label: &'static str) -> ScalarChallenge;
+}
+
+// Adapter implementing `Transcript` over the existing `FqSponge`/`FrSponge` pair. Every absorb and
+// squeeze mixes in a field element derived from its label, so relabelling or reordering two
+// same-position calls changes the transcript, not just the before/after calls. Scalars are
+// absorbed into the Fr-native sponge and its digest is folded into the Fq-sponge lazily, right
+// before the next domain tag, commitment absorb or challenge. Note this changes the exact challenge derivation versus the pre-refactor code, so
+// it is not a drop-in replacement for proofs generated against the old transcript.
+pub struct SpongeTranscript<EFqSponge, EFrSponge>
+{
+    fq_sponge: EFqSponge,
+    fr_sponge: EFrSponge,
+    fr_pending: bool,
+}
+
+impl<EFqSponge, EFrSponge> SpongeTranscript<EFqSponge, EFrSponge>
+{
+    pub fn new(fq_sponge: EFqSponge, fr_sponge: EFrSponge) -> Self
+    {
+        SpongeTranscript { fq_sponge, fr_sponge, fr_pending: false }
+    }
+}
+
+impl<E, EFqSponge, EFrSponge> SpongeTranscript<EFqSponge, EFrSponge>
+where
+    E: PairingEngine,
+    EFqSponge: FqSponge<E::Fq, E::G1Affine, E::Fr>,
+    EFrSponge: FrSponge<E::Fr>,
+{
+    fn sync_fr(&mut self)
+    {
+        if self.fr_pending
+        {
+            self.fq_sponge.absorb_fr(&[self.fr_sponge.digest()]);
+            self.fr_pending = false;
+        }
+    }
+}
+
+impl<E, EFqSponge, EFrSponge> Transcript<E> for SpongeTranscript<EFqSponge, EFrSponge>
+where
+    E: PairingEngine,
+    EFqSponge: FqSponge<E::Fq, E::G1Affine, E::Fr>,
+    EFrSponge: FrSponge<E::Fr>,
+{
+    fn new(index: &Index<E>) -> Self
+    {
+        SpongeTranscript::new
+        (
+            EFqSponge::new(index.fq_sponge_params.clone()),
+            EFrSponge::new(index.fr_sponge_params.clone())
+        )
+    }
+
+    fn domain_separator(&mut self, label: &'static str)
+    {
+        // fold in whatever the previous phase absorbed into the Fr-sponge before this phase's
+        // tag, so the tag always marks a clean boundary between phases
+        self.sync_fr();
+        self.fq_sponge.absorb_fr(&[label_to_field(label)]);
+    }
+
+    fn absorb_commitment(&mut self, label: &'static str, commitment: &E::G1Affine)
+    {
+        self.sync_fr();
+        self.fq_sponge.absorb_fr(&[label_to_field(label)]);
+        self.fq_sponge.absorb_g(&[*commitment]);
+    }
+
+    fn absorb_scalar(&mut self, label: &'static str, scalar: &E::Fr)
+    {
+        // absorb the label as its own element, same as every other operation here, instead of
+        // folding it additively into `scalar` -- an additive fold is invertible by whoever
+        // supplies `scalar` (subtract the known label constant back out), so it binds the label
+        // into the transcript in appearance only
+        self.fr_sponge.absorb(&label_to_field(label));
+        self.fr_sponge.absorb(scalar);
+        self.fr_pending = true;
+    }
+
+    fn squeeze_challenge(&mut self, label: &'static str) -> E::Fr
+    {
+        self.sync_fr();
+        self.fq_sponge.absorb_fr(&[label_to_field(label)]);
+        self.fq_sponge.challenge()
+    }
+
+    fn squeeze_challenge_128(&mut self, label: &'static str) -> ScalarChallenge
+    {
+        self.sync_fr();
+        self.fq_sponge.absorb_fr(&[label_to_field(label)]);
+        ScalarChallenge(self.fq_sponge.challenge128())
+    }
+}
+
+// Maps a domain-separation label to a field element by folding its bytes in, the same way
+// `ScalarChallenge::to_field` folds bits in, so no extra hash-to-field machinery is needed.
+fn label_to_field<F: Field>(label: &'static str) -> F
+{
+    let mut hash: u64 = 0;
+    for byte in label.bytes()
+    {
+        hash = hash.wrapping_mul(31).wrapping_add(byte as u64);
+    }
+
+    let mut acc = F::zero();
+    for i in (0..64).rev()
+    {
+        acc = acc.double();
+        if (hash >> i) & 1 == 1 { acc += &F::one(); }
+    }
+
+    acc
+}
+
+// Extension point for custom gates that span two adjacent rows (range checks, logic/XOR gates,
+// fixed-base scalar mul): a gate implements this to contribute the `(commitment, scalar)` pairs
+// its shifted-row (`zeta * omega`) evaluations add to the linearization, without `verify` needing
+// to know what gate it is. `evals.a_next`/`evals.b_next`/`evals.c_next` are exactly the values
+// such a gate needs to fold its successor-row constraint into the single `r_comm` opening,
+// instead of every gate kind needing its own opening proof. `Index::custom_gates` holds the gates
+// registered for a circuit; the built-in gate set (which only ever constrains a single row)
+// registers none, so folding an empty list is a no-op on existing circuits.
+pub trait CustomGateLinearization<E: PairingEngine>
+{
+    fn linearization_terms
+    (
+        &self,
+        oracles: &RandomOracles<E::Fr>,
+        evals: &ProofEvaluations<E::Fr>
+    ) -> Vec<(E::G1Affine, E::Fr)>;
+}
+
+// In hiding mode, folds a polynomial's random blinding commitment (a multiple of the vanishing
+// polynomial, committed separately by the prover) into its base commitment, so that two proofs
+// over the same witness don't carry identical commitments; outside hiding mode, `blind` is
+// ignored and the base commitment is returned unchanged.
+fn fold_blinding<E: PairingEngine>(base: E::G1Affine, blind: E::G1Affine, hiding: bool) -> E::G1Affine
+{
+    if hiding
+    {
+        (base.into_projective() + &blind.into_projective()).into_affine()
+    }
+    else
+    {
+        base
+    }
+}
+
+// Folds a custom gate's already-computed shifted-evaluation terms into `r_comm`, one scalar mul
+// per term. Split out from the `CustomGateLinearization` lookup itself so the fold's arithmetic
+// can be tested without needing a concrete `RandomOracles`/`ProofEvaluations` to hand a gate.
+fn fold_linearization_terms<E: PairingEngine>(r_comm: E::G1Affine, terms: impl Iterator<Item = (E::G1Affine, E::Fr)>) -> E::G1Affine
+{
+    terms
+        .fold(r_comm.into_projective(), |acc, (comm, scalar)| acc + &comm.mul(scalar))
+        .into_affine()
+}
+
+// PI(zeta) via the barycentric formula over the domain's roots of unity, instead of interpolating
+// the public input polynomial and evaluating it at zeta: for root w_i = omega^i,
+// L_i(zeta) = (w_i * z_h) / (n * (zeta - w_i)), so PI(zeta) = sum_i public_i * L_i(zeta). The
+// per-root denominators are batch inverted once rather than inverted one at a time.
+//
+// this formula is singular exactly when zeta lands on a domain root w_i (probability 0 for an
+// honest Fiat-Shamir zeta, but not something a batch inversion should be trusted to handle on its
+// own): there, L_i(w_i) = 1 and L_j(w_i) = 0 for j != i, so PI(zeta) is just that public input
+// directly.
+fn evaluate_public_input<F: PrimeField>(public: &[F], group_gen: F, n: F, zeta: F, z_h: F) -> F
+{
+    let on_domain_root = (0..public.len()).find(|&i| zeta == group_gen.pow(&[i as u64]));
+
+    match on_domain_root
+    {
+        Some(i) => public[i],
+        None =>
+        {
+            let mut denominators: Vec<F> = public.iter().enumerate().map(|(i, _)|
+            {
+                let w_i = group_gen.pow(&[i as u64]);
+                n * &(zeta - &w_i)
+            }).collect();
+            algebra::fields::batch_inversion(&mut denominators);
+
+            public.iter().zip(denominators.iter()).enumerate()
+                .fold(F::zero(), |acc, (i, (p, inv_denom))|
+                {
+                    let w_i = group_gen.pow(&[i as u64]);
+                    acc + &(*p * &w_i * &z_h * inv_denom)
+                })
+        }
+    }
+}
+
+// Combines the `(commitment, eval)` pairs that open at a single point into one pair, via
+// successive powers of `v`, so a whole opening point's worth of terms collapses into a single MSM
+// call instead of one full-width scalar mul per term.
+fn combine_with_v<E: PairingEngine>(entries: &[(E::G1Affine, E::Fr)], v: E::Fr) -> (E::G1Affine, E::Fr)
+{
+    let mut v_power = E::Fr::one();
+    let mut v_powers = Vec::with_capacity(entries.len());
+    let mut weighted_evals = Vec::with_capacity(entries.len());
+    for (_, eval) in entries
+    {
+        v_powers.push(v_power);
+        weighted_evals.push(*eval * &v_power);
+        v_power *= &v;
+    }
+
+    let bases: Vec<E::G1Affine> = entries.iter().map(|(comm, _)| *comm).collect();
+    let scalars: Vec<_> = v_powers.iter().map(|p| p.into_repr()).collect();
+    let comm = VariableBaseMSM::multi_scalar_mul(&bases, &scalars).into_affine();
+    let eval = weighted_evals.iter().fold(E::Fr::zero(), |acc, e| acc + e);
+
+    (comm, eval)
+}
 
 impl<E: PairingEngine> ProverProof<E>
 {
@@ -21,41 +284,132 @@ impl<E: PairingEngine> ProverProof<E>
     //     rng: randomness source context
     //     RETURN: verification status
     pub fn verify
-        <EFqSponge: FqSponge<E::Fq, E::G1Affine, E::Fr>,
-         EFrSponge: FrSponge<E::Fr>,
-        >
+        <T: Transcript<E>>
     (
         proofs: &Vec<ProverProof<E>>,
         index: &Index<E>,
-        rng: &mut dyn RngCore
+        // the combined check below is now driven entirely by the Fiat-Shamir challenge `u`
+        // derived from the proofs' own transcripts, so no external randomness is needed; kept for
+        // interface stability with callers
+        _rng: &mut dyn RngCore
     ) -> Result<bool, ProofError>
     {
-        let mut batch = Vec::new();
+        // outer Fiat-Shamir challenge binding together every proof in this batch. Deriving `u`
+        // from the proofs' transcripts, rather than drawing it from `rng`, means the combined
+        // check below is reproducible and auditable from the proofs alone. Accumulating each
+        // opening instance's contribution (two per proof, one at zeta and one at zeta*omega)
+        // weighted by successive powers u^0, u^1, u^2, ... mirrors the
+        // powers-of-alpha reducing-factor technique used to batch FRI oracles. A proof forged to
+        // pass the combined check only does so with probability N / |F|, since a cheating prover
+        // would need to guess `u` before it is derived from the (already fixed) transcripts.
+        let u = if proofs.len() <= 1
+        {
+            E::Fr::one()
+        }
+        else
+        {
+            let mut t = T::new(index);
+            t.domain_separator("plonk-batch-verify");
+            for proof in proofs.iter()
+            {
+                for p in proof.public.iter() { t.absorb_scalar("public", p); }
+                t.absorb_commitment("a", &proof.a_comm);
+                t.absorb_commitment("b", &proof.b_comm);
+                t.absorb_commitment("c", &proof.c_comm);
+                t.absorb_commitment("z", &proof.z_comm);
+                t.absorb_commitment("t-low", &proof.tlow_comm);
+                t.absorb_commitment("t-mid", &proof.tmid_comm);
+                t.absorb_commitment("t-high", &proof.thgh_comm);
+                t.absorb_commitment("proof1", &proof.proof1);
+                t.absorb_commitment("proof2", &proof.proof2);
+                for e in [proof.evals.a, proof.evals.b, proof.evals.c, proof.evals.sigma1, proof.evals.sigma2,
+                          proof.evals.r, proof.evals.z, proof.evals.a_next, proof.evals.b_next, proof.evals.c_next].iter()
+                {
+                    t.absorb_scalar("eval", e);
+                }
+                if index.hiding
+                {
+                    for blind in [proof.a_blind_comm, proof.b_blind_comm, proof.c_blind_comm, proof.z_blind_comm, proof.r_blind_comm].iter()
+                    {
+                        t.absorb_commitment("blind", blind);
+                    }
+                    t.absorb_scalar("r-blind", &proof.evals.r_blind);
+                }
+            }
+            t.squeeze_challenge_128("u").to_field(&index.endo)
+        };
+
+        // `f_acc`/`w_acc` accumulate every opening instance in the batch (each proof contributes
+        // one at zeta and one at zeta*omega) into the two running sums of the aggregated-opening
+        // check below, so the whole batch resolves to a single pairing check regardless of how
+        // many distinct points the individual instances open at.
+        let mut u_power = E::Fr::one();
+        let mut f_acc = E::G1Projective::zero();
+        let mut w_acc = E::G1Projective::zero();
         for proof in proofs.iter()
         {
             let proof = proof.clone();
-            let oracles = proof.oracles::<EFqSponge, EFrSponge>(index)?;
+            let oracles = proof.oracles::<T>(index)?;
             let zeta2 = oracles.zeta.pow(&[index.domain.size]);
             let zeta3 = zeta2.pow(&[index.domain.size]);
 
+            // the quotient was split into three equal-size chunks for committing, so
+            // reconstructing `t`'s commitment means re-weighting them by 1, zeta^n, zeta^2n; the
+            // `u` weighting is applied further below, one power of `u` for each of this proof's
+            // two opening instances as it is folded into the outer combined check
             let t_comm = VariableBaseMSM::multi_scalar_mul
             (
                 &[proof.tlow_comm, proof.tmid_comm, proof.thgh_comm],
                 &[E::Fr::one().into_repr(), zeta2.into_repr(), zeta3.into_repr()]
             ).into_affine();
 
+            // in hiding mode the prover folds a random `r_blind` polynomial, scaled by the
+            // vanishing polynomial, into the quotient so the opened evaluations reveal nothing
+            // about the witness; its commitment already carries its own `Z_H` factor, so it joins
+            // `t_comm` unscaled by the zeta-power ladder above
+            let t_comm = if index.hiding
+            {
+                (t_comm.into_projective() + &proof.r_blind_comm.into_projective()).into_affine()
+            }
+            else
+            {
+                t_comm
+            };
+
             let ab = (proof.evals.a + &(oracles.beta * &proof.evals.sigma1) + &oracles.gamma) *
                 &(proof.evals.b + &(oracles.beta * &proof.evals.sigma2) + &oracles.gamma) * &oracles.alpha;
 
+            // vanishing polynomial value at zeta, re-used both below and as the barycentric
+            // numerator factor for the public input evaluation
+            let z_h = zeta2 - &E::Fr::one();
+
+            let n = index.domain.size_as_field_element;
+            let public_evaluation = evaluate_public_input(&proof.public, index.domain.group_gen, n, oracles.zeta, z_h);
+
             let t =
                 (proof.evals.r +
-                &Evaluations::<E::Fr>::from_vec_and_domain(proof.public.clone(), index.domain).interpolate().evaluate(oracles.zeta) -
+                &public_evaluation -
                 &(ab * &(proof.evals.c + &oracles.gamma) * &proof.evals.z) -
-                &index.l1.evaluate(oracles.zeta)) / &(zeta2 - &E::Fr::one());
+                &index.l1.evaluate(oracles.zeta)) / &z_h;
+
+            // the commitment opens to `t(zeta) + r_blind(zeta) * z_h` once hiding folds
+            // `r_blind(x) * Z_H(x)` into the quotient, so the claimed evaluation must match
+            let t = if index.hiding { t + &(proof.evals.r_blind * &z_h) } else { t };
+
+            // in hiding mode the wire and permutation-accumulator polynomials each fold in their
+            // own random multiple of the vanishing polynomial before being committed, the same
+            // way the quotient's `r_blind` does above, so that two proofs over the same witness
+            // don't carry identical `a_comm`/`b_comm`/`c_comm`/`z_comm`; their opened evaluations
+            // (`evals.a`, `evals.b`, ...) already reflect the blinded polynomials, so only the
+            // commitments need folding here
+            let a_comm = fold_blinding::<E>(proof.a_comm, proof.a_blind_comm, index.hiding);
+            let b_comm = fold_blinding::<E>(proof.b_comm, proof.b_blind_comm, index.hiding);
+            let c_comm = fold_blinding::<E>(proof.c_comm, proof.c_blind_comm, index.hiding);
+            let z_comm = fold_blinding::<E>(proof.z_comm, proof.z_blind_comm, index.hiding);
 
             let r_comm = VariableBaseMSM::multi_scalar_mul
             (
-                &[index.qm_comm, index.ql_comm, index.qr_comm, index.qo_comm, index.qc_comm, proof.z_comm, index.sigma_comm[2]],
+                &[index.qm_comm, index.ql_comm, index.qr_comm, index.qo_comm, index.qc_comm, z_comm, index.sigma_comm[2]],
                 &[
                     (proof.evals.a * &proof.evals.b).into_repr(), proof.evals.a.into_repr(),
                     proof.evals.b.into_repr(), proof.evals.c.into_repr(), E::Fr::one().into_repr(),
@@ -68,32 +422,62 @@ impl<E: PairingEngine> ProverProof<E>
                     (ab * &oracles.beta * &proof.evals.z).into_repr(),
                 ]
             ).into_affine();
-    
-            batch.push
-            ((
-                oracles.zeta,
-                oracles.v,
-                vec!
-                [
-                    (t_comm,                t, None),
-                    (r_comm,                proof.evals.r, None),
-                    (proof.a_comm,          proof.evals.a, None),
-                    (proof.b_comm,          proof.evals.b, None),
-                    (proof.c_comm,          proof.evals.c, None),
-                    (index.sigma_comm[0],   proof.evals.sigma1, None),
-                    (index.sigma_comm[1],   proof.evals.sigma2, None),
+
+            // custom gates spanning two adjacent rows contribute their own shifted-evaluation
+            // terms here (drawing on `proof.evals.a_next`/`b_next`/`c_next` among other things),
+            // without this function needing to know what those gates are
+            let r_comm = fold_linearization_terms::<E>
+            (
+                r_comm,
+                index.custom_gates.iter().flat_map(|gate| gate.linearization_terms(&oracles, &proof.evals))
+            );
+
+            // this proof's opening at zeta: `t`, `r` and the wire/permutation commitments, folded
+            // into one (commitment, eval) pair via powers of `v`
+            let (group1_comm, group1_eval) = combine_with_v::<E>
+            (
+                &[
+                    (t_comm,              t),
+                    (r_comm,              proof.evals.r),
+                    (a_comm,              proof.evals.a),
+                    (b_comm,              proof.evals.b),
+                    (c_comm,              proof.evals.c),
+                    (index.sigma_comm[0], proof.evals.sigma1),
+                    (index.sigma_comm[1], proof.evals.sigma2),
                 ],
-                proof.proof1
-            ));
-            batch.push
-            ((
-                oracles.zeta * &index.domain.group_gen,
-                oracles.v,
-                vec![(proof.z_comm, proof.evals.z, None)],
-                proof.proof2
-            ));
+                oracles.v
+            );
+            // fold this opening instance into the aggregated check: for a KZG opening proof
+            // `pi` of `C` at `z` to `y`, `C - y*g + z*pi` and `pi` are exactly the two group
+            // elements a pairing check against `h`/`beta_h` compares; accumulating them here
+            // weighted by `u^index` is what lets every instance in the batch, at whatever point
+            // it opens at, collapse into the single pairing check performed after the loop
+            f_acc += &(group1_comm.into_projective() - &index.urs.g.mul(group1_eval) + &proof.proof1.mul(oracles.zeta)).mul(u_power);
+            w_acc += &proof.proof1.into_projective().mul(u_power);
+            u_power *= &u;
+
+            // the permutation accumulator `z` has always opened at zeta*omega; wire polynomials
+            // open there too, so gates spanning two adjacent rows (range checks,
+            // logic/XOR gates, fixed-base scalar mul) can constrain a row against its successor
+            let zeta_omega = oracles.zeta * &index.domain.group_gen;
+            let (group2_comm, group2_eval) = combine_with_v::<E>
+            (
+                &[
+                    (z_comm, proof.evals.z),
+                    (a_comm, proof.evals.a_next),
+                    (b_comm, proof.evals.b_next),
+                    (c_comm, proof.evals.c_next),
+                ],
+                oracles.v
+            );
+            f_acc += &(group2_comm.into_projective() - &index.urs.g.mul(group2_eval) + &proof.proof2.mul(zeta_omega)).mul(u_power);
+            w_acc += &proof.proof2.into_projective().mul(u_power);
+            u_power *= &u;
         }
-        match index.urs.verify(&batch, rng)
+
+        // every opening instance in the batch -- both points, every proof -- has now been folded
+        // into `f_acc`/`w_acc`, so one pairing check verifies the whole batch
+        match E::pairing(f_acc.into_affine(), index.urs.h) == E::pairing(w_acc.into_affine(), index.urs.beta_h)
         {
             false => Err(ProofError::OpenProof),
             true => Ok(true)
@@ -103,34 +487,234 @@ impl<E: PairingEngine> ProverProof<E>
     // This function queries random oracle values from non-interactive
     // argument context by verifier
     pub fn oracles
-        <EFqSponge: FqSponge<E::Fq, E::G1Affine, E::Fr>,
-         EFrSponge: FrSponge<E::Fr>,
-        >
+        <T: Transcript<E>>
     (
         &self,
         index: &Index<E>
     ) -> Result<RandomOracles<E::Fr>, ProofError>
     {
         let mut oracles = RandomOracles::<E::Fr>::zero();
-        let mut fq_sponge = EFqSponge::new(index.fq_sponge_params.clone());
+        let mut t = T::new(index);
 
-        // absorb the public input, a, b, c polycommitments into the argument
-        fq_sponge.absorb_fr(&self.public);
-        fq_sponge.absorb_g(&[self.a_comm, self.b_comm, self.c_comm]);
-        // sample beta, gamma oracles
-        oracles.beta = fq_sponge.challenge();
-        oracles.gamma = fq_sponge.challenge();
+        // phase 1: public input
+        t.domain_separator("plonk-public-input");
+        for p in self.public.iter() { t.absorb_scalar("public", p); }
 
-        // absorb the z commitment into the argument and query alpha
-        fq_sponge.absorb_g(&[self.z_comm]);
-        oracles.alpha = fq_sponge.challenge();
+        // phase 2: witness commitments
+        t.domain_separator("plonk-witness-commitments");
+        t.absorb_commitment("a", &self.a_comm);
+        t.absorb_commitment("b", &self.b_comm);
+        t.absorb_commitment("c", &self.c_comm);
+        // in hiding mode each wire polynomial folds in its own random multiple of the vanishing
+        // polynomial before being committed, same as the quotient's `r_blind` below; its
+        // commitment must be bound in before beta/gamma are sampled, same as the wire commitment
+        // it rides along with
+        if index.hiding
+        {
+            t.absorb_commitment("a-blind", &self.a_blind_comm);
+            t.absorb_commitment("b-blind", &self.b_blind_comm);
+            t.absorb_commitment("c-blind", &self.c_blind_comm);
+        }
+        // sample beta, gamma oracles as 128-bit challenges, expanded through the endomorphism
+        oracles.beta = t.squeeze_challenge_128("beta").to_field(&index.endo);
+        oracles.gamma = t.squeeze_challenge_128("gamma").to_field(&index.endo);
+
+        // phase 3: permutation commitment
+        t.domain_separator("plonk-permutation-commitment");
+        t.absorb_commitment("z", &self.z_comm);
+        if index.hiding
+        {
+            t.absorb_commitment("z-blind", &self.z_blind_comm);
+        }
+        oracles.alpha = t.squeeze_challenge_128("alpha").to_field(&index.endo);
 
-        // absorb the polycommitments into the argument and sample zeta
-        fq_sponge.absorb_g(&[self.tlow_comm, self.tmid_comm, self.thgh_comm]);
-        oracles.zeta = fq_sponge.challenge();
-        // query opening scaler challenge
-        oracles.v = fq_sponge.challenge();
+        // phase 4: quotient commitments
+        t.domain_separator("plonk-quotient-commitments");
+        t.absorb_commitment("t-low", &self.tlow_comm);
+        t.absorb_commitment("t-mid", &self.tmid_comm);
+        t.absorb_commitment("t-high", &self.thgh_comm);
+        // in hiding mode the random blinding commitment folded into the quotient must be bound
+        // into the transcript before zeta is sampled, same as the other quotient chunks
+        if index.hiding
+        {
+            t.absorb_commitment("t-blind", &self.r_blind_comm);
+        }
+        // the raw 128-bit challenge is only ever consumed through `to_field` below -- nothing in
+        // this file scales a commitment through the curve endomorphism directly, so there's
+        // nothing else worth keeping it around for
+        oracles.zeta = t.squeeze_challenge_128("zeta").to_field(&index.endo);
+
+        // phase 5: opening
+        t.domain_separator("plonk-opening");
+        // the next-row (zeta*omega) wire evaluations needed by gates spanning two adjacent rows
+        // are bound in here, so the opening challenge `v` is bound to them too
+        t.absorb_scalar("a-next", &self.evals.a_next);
+        t.absorb_scalar("b-next", &self.evals.b_next);
+        t.absorb_scalar("c-next", &self.evals.c_next);
+        oracles.v = t.squeeze_challenge_128("v").to_field(&index.endo);
 
         Ok(oracles)
     }
 }
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use algebra::bls12_381::{Bls12_381, Fr, G1Projective};
+    use algebra::UniformRand;
+    use rand::thread_rng;
+
+    #[test]
+    fn scalar_challenge_expansion_depends_on_lowest_two_bits()
+    {
+        let endo = Fr::one().double();
+        assert_ne!(ScalarChallenge(0).to_field(&endo), ScalarChallenge(1).to_field(&endo));
+        assert_ne!(ScalarChallenge(1).to_field(&endo), ScalarChallenge(2).to_field(&endo));
+    }
+
+    #[test]
+    fn label_to_field_differs_across_labels()
+    {
+        let a: Fr = label_to_field("plonk-public-input");
+        let b: Fr = label_to_field("plonk-witness-commitments");
+        assert_ne!(a, b);
+    }
+
+    // this is the exact function `verify` calls to fold a wire/permutation commitment's random
+    // blinding term in under hiding: two proofs over the identical witness (same `base`) must not
+    // be linkable through the commitment `verify` actually consumes, so independent blinding has
+    // to move the observed point, while a non-hiding index must ignore blinding altogether.
+    #[test]
+    fn fold_blinding_hides_identical_base_commitments_only_under_hiding()
+    {
+        let mut rng = thread_rng();
+        let base = G1Projective::rand(&mut rng).into_affine();
+        let blind_one = G1Projective::rand(&mut rng).into_affine();
+        let blind_two = G1Projective::rand(&mut rng).into_affine();
+
+        let hidden_one = fold_blinding::<Bls12_381>(base, blind_one, true);
+        let hidden_two = fold_blinding::<Bls12_381>(base, blind_two, true);
+        assert_ne!(hidden_one, hidden_two, "independent blinding must make the two commitments unlinkable");
+
+        let unhidden_one = fold_blinding::<Bls12_381>(base, blind_one, false);
+        let unhidden_two = fold_blinding::<Bls12_381>(base, blind_two, false);
+        assert_eq!(unhidden_one, base, "a non-hiding index must not fold blinding in at all");
+        assert_eq!(unhidden_one, unhidden_two, "two non-hiding proofs over the same witness still carry the same commitment");
+    }
+
+    // the domain {1, -1} (size 2, generator -1) has a direct Lagrange basis simple enough to
+    // write out by hand: L_0(x) = (x+1)/2, L_1(x) = (1-x)/2. This checks the barycentric formula
+    // against that closed form at a zeta off the domain, without needing a real domain/FFT type
+    // from this tree; the on-root case is covered by the next test.
+    #[test]
+    fn barycentric_public_input_matches_direct_lagrange_for_size_two_domain()
+    {
+        let one = Fr::one();
+        let two = one.double();
+        let n = two;
+        let group_gen = -one;
+        let p0 = one.double().double() + &one;
+        let p1 = p0 + &two;
+        let public = vec![p0, p1];
+
+        let zeta = two + &one;
+        let z_h = zeta.square() - &one;
+        let got = evaluate_public_input(&public, group_gen, n, zeta, z_h);
+
+        let l0 = (zeta + &one) / &two;
+        let l1 = (one - &zeta) / &two;
+        let expected = p0 * &l0 + &(p1 * &l1);
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn barycentric_public_input_returns_exact_value_on_domain_root()
+    {
+        let one = Fr::one();
+        let two = one.double();
+        let n = two;
+        let group_gen = -one;
+        let p0 = one.double().double() + &one;
+        let p1 = p0 + &two;
+        let public = vec![p0, p1];
+
+        // zeta landing exactly on the root w_1 = group_gen is the case the batch-inverted
+        // denominator is singular for; the formula must short-circuit to `public[1]` rather than
+        // divide by zero
+        let zeta = group_gen;
+        let z_h = zeta.square() - &one;
+        let got = evaluate_public_input(&public, group_gen, n, zeta, z_h);
+        assert_eq!(got, p1);
+    }
+
+    // mirrors the `f_acc`/`w_acc` accumulation `verify` performs across the whole proof batch,
+    // but against two KZG openings built directly from known polynomials rather than a full
+    // `ProverProof`: each opens independently to `C_i - y_i*g == pi_i * (tau - z_i)` (the pairing
+    // check with `h`/`beta_h` standing for the scalars `1`/`tau`), and folding them with `u^0`/`u^1`
+    // the same way `verify` does must still satisfy the combined check `f_acc == w_acc * tau` --
+    // i.e. the batch is exactly equivalent to the two independent openings it was built from.
+    #[test]
+    fn aggregated_opening_check_matches_two_independent_kzg_openings()
+    {
+        let mut rng = thread_rng();
+        let g = G1Projective::rand(&mut rng).into_affine();
+        let tau = Fr::rand(&mut rng);
+        let u = Fr::rand(&mut rng);
+
+        let make_instance = |coeffs: [Fr; 3], z: Fr|
+        {
+            let eval = |x: Fr| coeffs[0] + &(coeffs[1] * &x) + &(coeffs[2] * &x * &x);
+            let y = eval(z);
+            let comm = g.mul(eval(tau)).into_affine();
+            // (f(X) - y) / (X - z) for a degree-2 f, computed directly since the division is exact
+            let q1 = coeffs[2];
+            let q0 = coeffs[1] + &(z * &q1);
+            let proof = g.mul(q0 + &(q1 * &tau)).into_affine();
+            (comm, z, y, proof)
+        };
+
+        let instance_one = make_instance([Fr::rand(&mut rng), Fr::rand(&mut rng), Fr::rand(&mut rng)], Fr::rand(&mut rng));
+        let instance_two = make_instance([Fr::rand(&mut rng), Fr::rand(&mut rng), Fr::rand(&mut rng)], Fr::rand(&mut rng));
+
+        for (comm, z, y, proof) in vec![instance_one, instance_two]
+        {
+            assert_eq!((comm.into_projective() - &g.mul(y)).into_affine(), proof.mul(tau - &z).into_affine());
+        }
+
+        let mut u_power = Fr::one();
+        let mut f_acc = G1Projective::zero();
+        let mut w_acc = G1Projective::zero();
+        for (comm, z, y, proof) in vec![instance_one, instance_two]
+        {
+            f_acc += &(comm.into_projective() - &g.mul(y) + &proof.mul(z)).mul(u_power);
+            w_acc += &proof.into_projective().mul(u_power);
+            u_power *= &u;
+        }
+
+        assert_eq!(f_acc.into_affine(), w_acc.mul(tau).into_affine());
+    }
+
+    // the fold itself, without needing a `CustomGateLinearization` trait object or a concrete
+    // `RandomOracles`/`ProofEvaluations` to hand one: every (commitment, scalar) term contributes
+    // its own scalar mul into `r_comm`, and an empty term list (no custom gates registered, the
+    // case every existing circuit hits) must leave `r_comm` byte-for-byte unchanged.
+    #[test]
+    fn fold_linearization_terms_accumulates_every_custom_gate_term()
+    {
+        let mut rng = thread_rng();
+        let r_comm = G1Projective::rand(&mut rng).into_affine();
+        let comm_one = G1Projective::rand(&mut rng).into_affine();
+        let scalar_one = Fr::rand(&mut rng);
+        let comm_two = G1Projective::rand(&mut rng).into_affine();
+        let scalar_two = Fr::rand(&mut rng);
+
+        let terms = vec![(comm_one, scalar_one), (comm_two, scalar_two)];
+        let got = fold_linearization_terms::<Bls12_381>(r_comm, terms.into_iter());
+        let expected = (r_comm.into_projective() + &comm_one.mul(scalar_one) + &comm_two.mul(scalar_two)).into_affine();
+        assert_eq!(got, expected);
+
+        let unchanged = fold_linearization_terms::<Bls12_381>(r_comm, std::iter::empty());
+        assert_eq!(unchanged, r_comm);
+    }
+}